@@ -0,0 +1,56 @@
+use std::io::{BufRead, BufReader, Read};
+
+pub struct Dataset<R: Read> {
+    reader: BufReader<R>,
+    d_in: usize,
+    d_out: usize,
+}
+
+impl<R: Read> Dataset<R> {
+    pub fn new(reader: R, d_in: usize, d_out: usize) -> Dataset<R> {
+        Dataset {
+            reader: BufReader::new(reader),
+            d_in,
+            d_out,
+        }
+    }
+
+    pub fn next_record(&mut self) -> Option<(Vec<f64>, Vec<f64>)> {
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+            let bytes_read = self.reader.read_line(&mut line).ok()?;
+            if bytes_read == 0 {
+                return None;
+            }
+
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            let tokens = trimmed
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|token| !token.is_empty())
+                .map(|token| token.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>();
+
+            let tokens = match tokens {
+                Ok(tokens) if tokens.len() == self.d_in + self.d_out => tokens,
+                _ => continue,
+            };
+
+            let (x, y) = tokens.split_at(self.d_in);
+            return Some((x.to_vec(), y.to_vec()));
+        }
+    }
+}
+
+impl<R: Read> Iterator for Dataset<R> {
+    type Item = (Vec<f64>, Vec<f64>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record()
+    }
+}
@@ -1,5 +1,7 @@
 use core::fmt::Debug;
 use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
 use std::ops::Range;
 use std::rc::Rc;
 use std::sync::Arc;
@@ -8,10 +10,12 @@ use ndarray::{Array, Array1, Array2, Axis};
 use ndarray_rand::rand_distr::Uniform;
 use ndarray_rand::RandomExt;
 use plotly::{Bar, Plot};
-use rand::{prelude::*, thread_rng, Rng};
+use rand::{prelude::*, rngs::ThreadRng, thread_rng, Rng};
 
 use crate::activation::{Activation, Identity, Relu};
+use crate::dataset::Dataset;
 use crate::loss::{Loss, MSE};
+use crate::optimizer::{Optimizer, OptimizerState, Sgd};
 use crate::substrate::Substrate;
 
 #[derive(Debug)]
@@ -25,6 +29,7 @@ pub struct Layer {
     pub grad_w: Array2<f64>,
     pub grad_b: Array1<f64>,
     pub activation: Rc<dyn Activation>,
+    pub opt_state: OptimizerState,
 }
 
 impl Layer {
@@ -45,6 +50,7 @@ impl Layer {
             grad_w: Array2::zeros(w_shape),
             grad_b: Array::zeros(b_shape),
             activation,
+            opt_state: OptimizerState::zeros(w_shape, b_shape),
         }
     }
 
@@ -100,6 +106,11 @@ pub enum GradientRetention {
     Zero,
 }
 
+pub enum TrainingMode {
+    Gradient,
+    Annealing,
+}
+
 pub type LayerSchema = Vec<usize>;
 pub type Web = Vec<Layer>;
 
@@ -113,9 +124,13 @@ pub struct Manifold {
     output_activation: Rc<dyn Activation>,
     verbose: bool,
     loss: Rc<dyn Loss>,
+    optimizer: Rc<dyn Optimizer>,
     gradient_retention: GradientRetention,
+    training_mode: TrainingMode,
     learning_rate: f64,
     decay: f64,
+    t0: f64,
+    t1: f64,
     early_terminate: Box<dyn Fn(&Vec<f64>) -> bool>,
     epochs: usize,
     sample_size: usize,
@@ -139,9 +154,13 @@ impl Manifold {
             output_activation: Identity::new(),
             verbose: false,
             loss: MSE::new(),
+            optimizer: Sgd::new(),
             gradient_retention: GradientRetention::Roll,
+            training_mode: TrainingMode::Gradient,
             learning_rate: 0.001,
             decay: 1.,
+            t0: 10.,
+            t1: 0.01,
             early_terminate: Box::new(|_| false),
             epochs: 1000,
             sample_size: 10,
@@ -172,9 +191,13 @@ impl Manifold {
             output_activation: Identity::new(),
             verbose: false,
             loss: MSE::new(),
+            optimizer: Sgd::new(),
             gradient_retention: GradientRetention::Roll,
+            training_mode: TrainingMode::Gradient,
             learning_rate: 0.001,
             decay: 1.,
+            t0: 10.,
+            t1: 0.01,
             early_terminate: Box::new(|_| false),
             epochs: 1000,
             sample_size: 1,
@@ -202,11 +225,31 @@ impl Manifold {
         self
     }
 
+    pub fn set_optimizer(&mut self, optimizer: Rc<dyn Optimizer>) -> &mut Self {
+        self.optimizer = optimizer;
+        self
+    }
+
     pub fn set_gradient_retention(&mut self, method: GradientRetention) -> &mut Self {
         self.gradient_retention = method;
         self
     }
 
+    pub fn set_training_mode(&mut self, mode: TrainingMode) -> &mut Self {
+        self.training_mode = mode;
+        self
+    }
+
+    pub fn set_initial_temperature(&mut self, t0: f64) -> &mut Self {
+        self.t0 = t0;
+        self
+    }
+
+    pub fn set_final_temperature(&mut self, t1: f64) -> &mut Self {
+        self.t1 = t1;
+        self
+    }
+
     pub fn set_learning_rate(&mut self, rate: f64) -> &mut Self {
         self.learning_rate = rate;
         self
@@ -314,21 +357,49 @@ impl Manifold {
         Array2::zeros((1, l)).mapv_into(|_| xvd.pop_front().unwrap())
     }
 
-    pub fn forward(&mut self, xv: Vec<f64>) -> Array1<f64> {
-        let mut x = self.prepare(xv);
+    fn prepare_batch(&self, x: Vec<Vec<f64>>) -> Array2<f64> {
+        let n = x.len();
+        let d = x[0].len();
+        assert!(
+            x.iter().all(|row| row.len() == d),
+            "prepare_batch: every row in a batch must have the same length (expected {}, found a ragged row)",
+            d
+        );
+        let flat = x.into_iter().flatten().collect::<Vec<f64>>();
+        Array2::from_shape_vec((n, d), flat).unwrap()
+    }
+
+    fn loss_over_rows(&self, y_pred: &Array2<f64>, y: &Array2<f64>) -> f64 {
+        let n = y_pred.nrows();
+        let total = (0..n).fold(0., |a, i| {
+            a + self.loss.a(y_pred.row(i).to_owned(), y.row(i).to_owned())
+        });
+        total / n as f64
+    }
+
+    pub fn forward_batch(&mut self, x: Array2<f64>) -> Array2<f64> {
+        let mut x = x;
         for layer in self.web.iter_mut() {
             x = layer.forward(x);
         }
-        let shape = x.len();
-        x.into_shape(shape).unwrap()
+        x
     }
 
-    pub fn backwards(&mut self, y_pred: Array1<f64>, y: Vec<f64>, loss: Rc<dyn Loss>) {
-        let y_target = Array1::from(y);
-        let grad_output_i = loss.d(y_pred, y_target);
+    pub fn forward(&mut self, xv: Vec<f64>) -> Array1<f64> {
+        let x = self.prepare(xv);
+        let y = self.forward_batch(x);
+        let shape = y.len();
+        y.into_shape(shape).unwrap()
+    }
 
-        let grad_output_shape = (1, grad_output_i.len());
-        let mut grad_output = grad_output_i.into_shape(grad_output_shape).unwrap();
+    pub fn backwards_batch(&mut self, y_pred: Array2<f64>, y: Array2<f64>, loss: Rc<dyn Loss>) {
+        let n = y_pred.nrows();
+        let mut grad_output = Array2::zeros(y_pred.raw_dim());
+
+        for i in 0..n {
+            let grad_row = loss.d(y_pred.row(i).to_owned(), y.row(i).to_owned());
+            grad_output.row_mut(i).assign(&grad_row);
+        }
 
         for layer in self.web.iter_mut().rev() {
             grad_output = layer.backward(grad_output);
@@ -336,20 +407,24 @@ impl Manifold {
             let grad_b_dim = layer.grad_b.raw_dim();
             let grad_w_dim = layer.grad_w.raw_dim();
 
-            let mut b_grad_reshaped = layer.grad_b.to_owned().insert_axis(Axis(1));
+            self.optimizer.begin_step(&mut layer.opt_state);
+
+            let mut adapted_w = self.optimizer.adapt_w(&layer.grad_w, &mut layer.opt_state);
+            let mut adapted_b = self
+                .optimizer
+                .adapt_b(&layer.grad_b, &mut layer.opt_state)
+                .insert_axis(Axis(1));
             let mut b_link_reshaped = layer.bi.to_owned().insert_axis(Axis(1));
 
             self.substrate
-                .highspeed(&mut layer.grad_w, &mut layer.wi, self.learning_rate);
-            self.substrate.highspeed(
-                &mut b_grad_reshaped,
-                &mut b_link_reshaped,
-                self.learning_rate,
-            );
+                .highspeed(&mut adapted_w, &mut layer.wi, self.learning_rate);
+            self.substrate
+                .highspeed(&mut adapted_b, &mut b_link_reshaped, self.learning_rate);
 
             layer
                 .shift_bias(&b_link_reshaped.remove_axis(Axis(1)))
-                .assign_grad_b(b_grad_reshaped.remove_axis(Axis(1)))
+                .assign_grad_w(adapted_w)
+                .assign_grad_b(adapted_b.remove_axis(Axis(1)))
                 .gather(&self.substrate);
 
             match self.gradient_retention {
@@ -363,7 +438,18 @@ impl Manifold {
         }
     }
 
+    pub fn backwards(&mut self, y_pred: Array1<f64>, y: Vec<f64>, loss: Rc<dyn Loss>) {
+        let d = y_pred.len();
+        let y_pred_batch = y_pred.into_shape((1, d)).unwrap();
+        let y_batch = Array2::from_shape_vec((1, y.len()), y).unwrap();
+        self.backwards_batch(y_pred_batch, y_batch, loss);
+    }
+
     pub fn train(&mut self, x: Vec<Vec<f64>>, y: Vec<Vec<f64>>) -> &mut Self {
+        if let TrainingMode::Annealing = self.training_mode {
+            return self.anneal(x, y);
+        }
+
         let xy = x
             .into_iter()
             .zip(y.into_iter())
@@ -373,21 +459,80 @@ impl Manifold {
         for epoch in 0..self.epochs {
             let sample = xy
                 .choose_multiple(&mut rng, self.sample_size)
-                .collect::<Vec<&(Vec<f64>, Vec<f64>)>>();
-            let mut total_loss: Vec<f64> = vec![];
+                .cloned()
+                .collect::<Vec<(Vec<f64>, Vec<f64>)>>();
+
+            let avg_loss = self.train_on_sample(sample);
+            self.losses.push(avg_loss);
+
+            if (self.early_terminate)(&self.losses) {
+                if self.verbose {
+                    println!("Early termination condition met.");
+                }
+
+                break;
+            }
+
+            if self.verbose {
+                println!("({}/{}) Loss = {}", epoch, self.epochs, avg_loss);
+            }
+        }
+
+        self
+    }
+
+    pub fn train_stream<R: Read>(&mut self, reader: R) -> &mut Self {
+        let mut dataset = Dataset::new(reader, self.d_in, self.d_out);
+        let reservoir_capacity = self.sample_size * 10;
+        let mut reservoir: Vec<(Vec<f64>, Vec<f64>)> = Vec::with_capacity(reservoir_capacity);
+        let mut write_idx = 0;
+        let mut rng = thread_rng();
+
+        while reservoir.len() < reservoir_capacity {
+            match dataset.next_record() {
+                Some(record) => reservoir.push(record),
+                None => break,
+            }
+        }
+
+        if reservoir.is_empty() {
+            if self.verbose {
+                println!("No records read from the stream; nothing to train on.");
+            }
+
+            return self;
+        }
 
-            for &xy in sample.iter() {
-                let (x, y) = xy.clone();
+        let annealing = matches!(self.training_mode, TrainingMode::Annealing);
+        let mut current_loss = if annealing {
+            self.gather();
+            let sample_size = self.sample_size.min(reservoir.len());
+            let sample = reservoir
+                .choose_multiple(&mut rng, sample_size)
+                .collect::<Vec<&(Vec<f64>, Vec<f64>)>>();
+            self.batch_loss(&sample)
+        } else {
+            0.
+        };
 
-                let y_pred = self.forward(x);
-                total_loss.push(self.loss.a(y_pred.clone(), Array1::from(y.clone())));
-                self.backwards(y_pred, y, Rc::clone(&self.loss));
+        for epoch in 0..self.epochs {
+            if let Some(record) = dataset.next_record() {
+                reservoir[write_idx] = record;
+                write_idx = (write_idx + 1) % reservoir.len();
             }
 
-            self.learning_rate *= self.decay;
+            let avg_loss = if annealing {
+                current_loss = self.anneal_step(&reservoir, &mut rng, epoch, current_loss);
+                current_loss
+            } else {
+                let sample_size = self.sample_size.min(reservoir.len());
+                let sample = reservoir
+                    .choose_multiple(&mut rng, sample_size)
+                    .cloned()
+                    .collect::<Vec<(Vec<f64>, Vec<f64>)>>();
+                self.train_on_sample(sample)
+            };
 
-            let ct = total_loss.len() as f64;
-            let avg_loss = total_loss.into_iter().fold(0., |a, v| a + v) / ct;
             self.losses.push(avg_loss);
 
             if (self.early_terminate)(&self.losses) {
@@ -406,6 +551,141 @@ impl Manifold {
         self
     }
 
+    fn train_on_sample(&mut self, sample: Vec<(Vec<f64>, Vec<f64>)>) -> f64 {
+        let avg_loss = if sample.is_empty() {
+            f64::NAN
+        } else {
+            let (x_sample, y_sample): (Vec<Vec<f64>>, Vec<Vec<f64>>) = sample.into_iter().unzip();
+
+            let x_batch = self.prepare_batch(x_sample);
+            let y_batch = self.prepare_batch(y_sample);
+
+            let y_pred = self.forward_batch(x_batch);
+            let avg_loss = self.loss_over_rows(&y_pred, &y_batch);
+            self.backwards_batch(y_pred, y_batch, Rc::clone(&self.loss));
+
+            avg_loss
+        };
+
+        self.learning_rate *= self.decay;
+
+        avg_loss
+    }
+
+    fn batch_loss(&mut self, batch: &[&(Vec<f64>, Vec<f64>)]) -> f64 {
+        let loss = Rc::clone(&self.loss);
+        let mut total_loss = 0.;
+
+        for &(x, y) in batch.iter() {
+            let y_pred = self.forward(x.clone());
+            total_loss += loss.a(y_pred, Array1::from(y.clone()));
+        }
+
+        total_loss / batch.len() as f64
+    }
+
+    fn perturb_layer(
+        &mut self,
+        layer_idx: usize,
+        rng: &mut ThreadRng,
+    ) -> (Array2<usize>, Array1<usize>) {
+        let size = self.substrate.size as i64;
+        let layer = &mut self.web[layer_idx];
+
+        let wi_before = layer.wi.clone();
+        let bi_before = layer.bi.clone();
+
+        let w_len = layer.wi.len();
+        let num_w = ((w_len / 4).max(1)).min(w_len);
+        for _ in 0..num_w {
+            let idx = rng.gen_range(0..w_len);
+            let delta = rng.gen_range(-4..=4_i64);
+            let slot = layer.wi.as_slice_mut().unwrap();
+            slot[idx] = (slot[idx] as i64 + delta).clamp(0, size - 1) as usize;
+        }
+
+        let b_len = layer.bi.len();
+        let num_b = ((b_len / 4).max(1)).min(b_len);
+        for _ in 0..num_b {
+            let idx = rng.gen_range(0..b_len);
+            let delta = rng.gen_range(-4..=4_i64);
+            let slot = layer.bi.as_slice_mut().unwrap();
+            slot[idx] = (slot[idx] as i64 + delta).clamp(0, size - 1) as usize;
+        }
+
+        (wi_before, bi_before)
+    }
+
+    pub fn anneal(&mut self, x: Vec<Vec<f64>>, y: Vec<Vec<f64>>) -> &mut Self {
+        let xy = x
+            .into_iter()
+            .zip(y.into_iter())
+            .collect::<Vec<(Vec<f64>, Vec<f64>)>>();
+        let mut rng = thread_rng();
+
+        self.gather();
+        let starting_sample = xy
+            .choose_multiple(&mut rng, self.sample_size)
+            .collect::<Vec<&(Vec<f64>, Vec<f64>)>>();
+        let mut current_loss = self.batch_loss(&starting_sample);
+
+        for epoch in 0..self.epochs {
+            current_loss = self.anneal_step(&xy, &mut rng, epoch, current_loss);
+            self.losses.push(current_loss);
+
+            if (self.early_terminate)(&self.losses) {
+                if self.verbose {
+                    println!("Early termination condition met.");
+                }
+
+                break;
+            }
+
+            if self.verbose {
+                println!("({}/{}) Loss = {}", epoch, self.epochs, current_loss);
+            }
+        }
+
+        self
+    }
+
+    fn anneal_step(
+        &mut self,
+        records: &[(Vec<f64>, Vec<f64>)],
+        rng: &mut ThreadRng,
+        epoch: usize,
+        current_loss: f64,
+    ) -> f64 {
+        let p = epoch as f64 / self.epochs as f64;
+        let temperature = self.t0.powf(1. - p) * self.t1.powf(p);
+
+        let layer_idx = rng.gen_range(0..self.web.len());
+        let (wi_before, bi_before) = self.perturb_layer(layer_idx, rng);
+        self.gather();
+
+        let sample_size = self.sample_size.min(records.len());
+        let sample = records
+            .choose_multiple(rng, sample_size)
+            .collect::<Vec<&(Vec<f64>, Vec<f64>)>>();
+        let new_loss = self.batch_loss(&sample);
+
+        let accept = if new_loss < current_loss {
+            true
+        } else {
+            let p_accept = ((current_loss - new_loss) / temperature).exp();
+            rng.gen::<f64>() < p_accept
+        };
+
+        if accept {
+            new_loss
+        } else {
+            self.web[layer_idx].wi = wi_before;
+            self.web[layer_idx].bi = bi_before;
+            self.gather();
+            current_loss
+        }
+    }
+
     pub fn loss_graph(&mut self) -> &mut Self {
         let mut plot = Plot::new();
 
@@ -418,4 +698,169 @@ impl Manifold {
 
         self
     }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+
+        writeln!(w, "{}", MODEL_FORMAT_VERSION)?;
+        writeln!(w, "{} {}", self.d_in, self.d_out)?;
+        writeln!(w, "{}", self.layers.len())?;
+        writeln!(w, "{}", join_usize(&self.layers))?;
+        writeln!(w, "{:?}", self.hidden_activation)?;
+        writeln!(w, "{:?}", self.output_activation)?;
+        writeln!(w, "{:?}", self.loss)?;
+        writeln!(w, "{} {}", self.learning_rate, self.decay)?;
+        writeln!(w, "{}", self.substrate.size)?;
+        writeln!(w, "{}", self.web.len())?;
+
+        for layer in self.web.iter() {
+            let (rows, cols) = layer.wi.dim();
+            writeln!(w, "{} {}", rows, cols)?;
+            writeln!(w, "{}", join_usize(layer.wi.as_slice().unwrap()))?;
+            writeln!(w, "{}", layer.bi.len())?;
+            writeln!(w, "{}", join_usize(layer.bi.as_slice().unwrap()))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn load(substrate: Arc<Substrate>, path: &str) -> io::Result<Manifold> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+
+        let version = read_line(&mut lines)?;
+        if version != MODEL_FORMAT_VERSION {
+            return Err(invalid_data(format!(
+                "unrecognised model format: {}",
+                version
+            )));
+        }
+
+        let dims = read_line(&mut lines)?;
+        let mut dims = dims.split_whitespace();
+        let d_in = parse_usize(dims.next())?;
+        let d_out = parse_usize(dims.next())?;
+
+        let num_layers = parse_usize(Some(read_line(&mut lines)?.as_str()))?;
+        let layers = read_line(&mut lines)?
+            .split_whitespace()
+            .map(|v| v.parse::<usize>())
+            .collect::<Result<Vec<usize>, _>>()
+            .map_err(|e| invalid_data(e.to_string()))?;
+        if layers.len() != num_layers {
+            return Err(invalid_data("layer schema length mismatch"));
+        }
+
+        let hidden_activation = activation_from_name(&read_line(&mut lines)?)?;
+        let output_activation = activation_from_name(&read_line(&mut lines)?)?;
+        let loss = loss_from_name(&read_line(&mut lines)?)?;
+
+        let rates = read_line(&mut lines)?;
+        let mut rates = rates.split_whitespace();
+        let learning_rate = parse_f64(rates.next())?;
+        let decay = parse_f64(rates.next())?;
+
+        let trained_substrate_size = parse_usize(Some(read_line(&mut lines)?.as_str()))?;
+        if trained_substrate_size != substrate.size {
+            return Err(invalid_data(format!(
+                "substrate size mismatch: model was trained against a substrate of size {} but {} was provided",
+                trained_substrate_size, substrate.size
+            )));
+        }
+
+        let mut manifold = Manifold::new(substrate, d_in, d_out, layers);
+        manifold
+            .set_hidden_activation(hidden_activation)
+            .set_output_activation(output_activation)
+            .set_loss(loss)
+            .set_learning_rate(learning_rate)
+            .set_decay(decay)
+            .weave();
+
+        let num_web_layers = parse_usize(Some(read_line(&mut lines)?.as_str()))?;
+        if num_web_layers != manifold.web.len() {
+            return Err(invalid_data("layer count mismatch"));
+        }
+
+        for layer in manifold.web.iter_mut() {
+            let wi_dims = read_line(&mut lines)?;
+            let mut wi_dims = wi_dims.split_whitespace();
+            let rows = parse_usize(wi_dims.next())?;
+            let cols = parse_usize(wi_dims.next())?;
+
+            let wi_values = parse_usize_row(&read_line(&mut lines)?)?;
+            layer.wi = Array2::from_shape_vec((rows, cols), wi_values)
+                .map_err(|e| invalid_data(e.to_string()))?;
+
+            let b_len = parse_usize(Some(read_line(&mut lines)?.as_str()))?;
+            let bi_values = parse_usize_row(&read_line(&mut lines)?)?;
+            layer.bi = Array1::from_shape_vec(b_len, bi_values)
+                .map_err(|e| invalid_data(e.to_string()))?;
+        }
+
+        manifold.gather();
+
+        Ok(manifold)
+    }
+}
+
+const MODEL_FORMAT_VERSION: &str = "plural.manifold.v1";
+
+fn join_usize(values: &[usize]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn read_line(lines: &mut std::io::Lines<BufReader<File>>) -> io::Result<String> {
+    lines
+        .next()
+        .ok_or_else(|| invalid_data("unexpected end of model file"))?
+}
+
+fn parse_usize(value: Option<&str>) -> io::Result<usize> {
+    value
+        .ok_or_else(|| invalid_data("missing field in model file"))?
+        .parse()
+        .map_err(|_| invalid_data("expected an integer in model file"))
+}
+
+fn parse_f64(value: Option<&str>) -> io::Result<f64> {
+    value
+        .ok_or_else(|| invalid_data("missing field in model file"))?
+        .parse()
+        .map_err(|_| invalid_data("expected a float in model file"))
+}
+
+fn parse_usize_row(line: &str) -> io::Result<Vec<usize>> {
+    line.split_whitespace()
+        .map(|v| v.parse::<usize>())
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|e| invalid_data(e.to_string()))
+}
+
+fn activation_from_name(name: &str) -> io::Result<Rc<dyn Activation>> {
+    match name {
+        "Relu" => Ok(Relu::new()),
+        "Identity" => Ok(Identity::new()),
+        _ => Err(invalid_data(format!(
+            "cannot reconstruct unknown activation from model file: {}",
+            name
+        ))),
+    }
+}
+
+fn loss_from_name(name: &str) -> io::Result<Rc<dyn Loss>> {
+    match name {
+        "MSE" => Ok(MSE::new()),
+        _ => Err(invalid_data(format!(
+            "cannot reconstruct unknown loss from model file: {}",
+            name
+        ))),
+    }
 }
@@ -0,0 +1,154 @@
+use core::fmt::Debug;
+use std::rc::Rc;
+
+use ndarray::{Array1, Array2};
+
+#[derive(Debug)]
+pub struct OptimizerState {
+    pub vw: Array2<f64>,
+    pub vb: Array1<f64>,
+    pub sw: Array2<f64>,
+    pub sb: Array1<f64>,
+    pub t: usize,
+}
+
+impl OptimizerState {
+    pub fn zeros(w_shape: (usize, usize), b_shape: usize) -> OptimizerState {
+        OptimizerState {
+            vw: Array2::zeros(w_shape),
+            vb: Array1::zeros(b_shape),
+            sw: Array2::zeros(w_shape),
+            sb: Array1::zeros(b_shape),
+            t: 0,
+        }
+    }
+}
+
+pub trait Optimizer: Debug {
+    fn begin_step(&self, _state: &mut OptimizerState) {}
+    fn adapt_w(&self, grad_w: &Array2<f64>, state: &mut OptimizerState) -> Array2<f64>;
+    fn adapt_b(&self, grad_b: &Array1<f64>, state: &mut OptimizerState) -> Array1<f64>;
+}
+
+#[derive(Debug)]
+pub struct Sgd;
+
+impl Sgd {
+    pub fn new() -> Rc<dyn Optimizer> {
+        Rc::new(Sgd)
+    }
+}
+
+impl Optimizer for Sgd {
+    fn adapt_w(&self, grad_w: &Array2<f64>, _state: &mut OptimizerState) -> Array2<f64> {
+        grad_w.clone()
+    }
+
+    fn adapt_b(&self, grad_b: &Array1<f64>, _state: &mut OptimizerState) -> Array1<f64> {
+        grad_b.clone()
+    }
+}
+
+#[derive(Debug)]
+pub struct Momentum {
+    mu: f64,
+}
+
+impl Momentum {
+    pub fn new(mu: f64) -> Rc<dyn Optimizer> {
+        Rc::new(Momentum { mu })
+    }
+}
+
+impl Optimizer for Momentum {
+    fn adapt_w(&self, grad_w: &Array2<f64>, state: &mut OptimizerState) -> Array2<f64> {
+        state.vw = self.mu * &state.vw + grad_w;
+        state.vw.clone()
+    }
+
+    fn adapt_b(&self, grad_b: &Array1<f64>, state: &mut OptimizerState) -> Array1<f64> {
+        state.vb = self.mu * &state.vb + grad_b;
+        state.vb.clone()
+    }
+}
+
+#[derive(Debug)]
+pub struct Adam {
+    beta1: f64,
+    beta2: f64,
+    epsilon: f64,
+}
+
+impl Adam {
+    pub fn new() -> Rc<dyn Optimizer> {
+        Rc::new(Adam {
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+        })
+    }
+
+    pub fn with_params(beta1: f64, beta2: f64, epsilon: f64) -> Rc<dyn Optimizer> {
+        Rc::new(Adam {
+            beta1,
+            beta2,
+            epsilon,
+        })
+    }
+}
+
+impl Optimizer for Adam {
+    fn begin_step(&self, state: &mut OptimizerState) {
+        state.t += 1;
+    }
+
+    fn adapt_w(&self, grad_w: &Array2<f64>, state: &mut OptimizerState) -> Array2<f64> {
+        let t = state.t as i32;
+
+        state.mw_w(self.beta1, self.beta2, grad_w);
+        state.bias_corrected_step_w(self.beta1, self.beta2, self.epsilon, t)
+    }
+
+    fn adapt_b(&self, grad_b: &Array1<f64>, state: &mut OptimizerState) -> Array1<f64> {
+        let t = state.t as i32;
+
+        state.mw_b(self.beta1, self.beta2, grad_b);
+        state.bias_corrected_step_b(self.beta1, self.beta2, self.epsilon, t)
+    }
+}
+
+impl OptimizerState {
+    fn mw_w(&mut self, beta1: f64, beta2: f64, grad_w: &Array2<f64>) {
+        self.vw = beta1 * &self.vw + (1. - beta1) * grad_w;
+        self.sw = beta2 * &self.sw + (1. - beta2) * grad_w.mapv(|g| g * g);
+    }
+
+    fn mw_b(&mut self, beta1: f64, beta2: f64, grad_b: &Array1<f64>) {
+        self.vb = beta1 * &self.vb + (1. - beta1) * grad_b;
+        self.sb = beta2 * &self.sb + (1. - beta2) * grad_b.mapv(|g| g * g);
+    }
+
+    fn bias_corrected_step_w(
+        &self,
+        beta1: f64,
+        beta2: f64,
+        epsilon: f64,
+        t: i32,
+    ) -> Array2<f64> {
+        let m_hat = &self.vw / (1. - beta1.powi(t));
+        let s_hat = &self.sw / (1. - beta2.powi(t));
+        m_hat / (s_hat.mapv(f64::sqrt) + epsilon)
+    }
+
+    fn bias_corrected_step_b(
+        &self,
+        beta1: f64,
+        beta2: f64,
+        epsilon: f64,
+        t: i32,
+    ) -> Array1<f64> {
+        let m_hat = &self.vb / (1. - beta1.powi(t));
+        let s_hat = &self.sb / (1. - beta2.powi(t));
+        m_hat / (s_hat.mapv(f64::sqrt) + epsilon)
+    }
+}